@@ -0,0 +1,81 @@
+use crate::lexer::{LexError, LexErrorKind};
+use std::ops::Range;
+
+/// Maps byte offsets into a source file back to 1-based (line, column)
+/// pairs, for turning a lexer span into something a person can act on.
+pub struct LineIndex {
+    /// Byte offset of each `\n` in the source, in ascending order.
+    newlines: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let newlines = source
+            .bytes()
+            .enumerate()
+            .filter_map(|(i, b)| (b == b'\n').then_some(i))
+            .collect();
+        Self { newlines }
+    }
+
+    /// The 1-based line and column of `offset`, which must be a byte index
+    /// into the source this index was built from.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        // `nl < offset`, not `<=`: a newline's own offset is still on the
+        // line it terminates, not the line after it. Getting this wrong
+        // makes `offset - line_start` underflow whenever `offset` lands
+        // exactly on a `\n`, which is exactly where `Newline`-spanned
+        // errors like `InconsistentDedent` point.
+        let line = self.newlines.partition_point(|&nl| nl < offset);
+        let line_start = if line == 0 { 0 } else { self.newlines[line - 1] + 1 };
+        (line + 1, offset - line_start + 1)
+    }
+}
+
+fn message(kind: LexErrorKind) -> &'static str {
+    match kind {
+        LexErrorKind::InconsistentDedent => "dedent does not match any outer indentation level",
+        LexErrorKind::TabSpaceMix => "indentation mixes tabs and spaces ambiguously",
+        LexErrorKind::UnterminatedString => "unterminated string literal",
+        LexErrorKind::UnmatchedBrace => "unmatched '{' or '}' in f-string",
+        LexErrorKind::InvalidToken => "invalid token",
+    }
+}
+
+/// Renders `error` as `file:line:col: message`, followed by the offending
+/// source line and a caret under the column the error starts at.
+///
+/// `span` is the one paired with the error in the token stream, not
+/// `error.span` — they usually agree, but the stream's span is always
+/// accurate even for the placeholder `LexError::default()` `logos` itself
+/// produces when no token rule matches at all.
+pub fn render(path: &str, source: &str, index: &LineIndex, span: Range<usize>, error: &LexError) -> String {
+    let (line, col) = index.line_col(span.start);
+    let source_line = source.lines().nth(line - 1).unwrap_or("");
+    let caret = " ".repeat(col.saturating_sub(1)) + "^";
+
+    format!("{path}:{line}:{col}: {}\n{source_line}\n{caret}", message(error.kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{lex, IndentStyle};
+
+    /// Regression test for a dedent error rendered from the `Newline`
+    /// token's own span, whose start is the newline byte that ends the
+    /// *previous* line. `line_col` must not underflow on that offset.
+    #[test]
+    fn renders_inconsistent_dedent_without_panicking() {
+        let source = "def f():\n    def g():\n        return 1\n      return 2\n";
+        let index = LineIndex::new(source);
+
+        let (error, span) = lex(source, IndentStyle::default())
+            .into_iter()
+            .find_map(|(token, span)| token.err().map(|error| (error, span)))
+            .expect("this dedent doesn't line up with any enclosing indentation level");
+
+        let rendered = render("<test>", source, &index, span, &error);
+        assert!(rendered.contains("dedent does not match any outer indentation level"));
+    }
+}
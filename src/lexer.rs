@@ -1,8 +1,43 @@
 use logos::Logos;
+use std::collections::VecDeque;
 use std::ops::Range;
 
+/// What went wrong while producing a token, independent of where.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexErrorKind {
+    /// A dedent's column doesn't match any enclosing indentation level.
+    InconsistentDedent,
+    /// A line's indentation mixes tabs and spaces in a way that would
+    /// compare differently under another tab width.
+    TabSpaceMix,
+    /// A string, bytes, or f-string literal was never closed before EOF.
+    UnterminatedString,
+    /// A `{`/`}` inside an f-string has no matching counterpart.
+    UnmatchedBrace,
+    /// No token rule matched at all.
+    InvalidToken,
+}
+
+/// A lexing failure, self-contained so it can be reported without needing
+/// the token stream it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub span: Range<usize>,
+    pub kind: LexErrorKind,
+}
+
+impl Default for LexError {
+    /// Used by `logos` itself when no token rule matches at all; the span is
+    /// a placeholder since `logos` doesn't hand callbacks one to fill in.
+    /// Callers should prefer the span paired with the token in the stream.
+    fn default() -> Self {
+        LexError { span: 0..0, kind: LexErrorKind::InvalidToken }
+    }
+}
+
 #[derive(Logos, Debug, PartialEq)]
 #[logos(skip r"[ \t]+")]
+#[logos(error = LexError)]
 pub enum Token<'a> {
     #[token("def")]
     Definition,
@@ -33,43 +68,750 @@ pub enum Token<'a> {
 
     #[regex("[0-9]+", |lex| lex.slice().parse().ok())]
     Integer(i64),
+
+    /// A `str` literal: an optional `r` prefix followed by `'`, `"`, `'''`,
+    /// or `"""`. The quotes and prefix are consumed by `lex_str`, which scans
+    /// the body by hand since `logos`'s regexes can't track escapes or
+    /// triple-quote terminators on their own.
+    #[regex(r#"(?i:r)?("""|'''|"|')"#, lex_str)]
+    Str(StrBody<'a>),
+
+    /// A `bytes` literal: `b`, `rb`, or `br` (any case) followed by a quote.
+    #[regex(r#"(?i:b|rb|br)("""|'''|"|')"#, lex_bytes)]
+    Bytes(StrBody<'a>),
+
+    /// The opening `f`/`rf`/`fr` prefix plus quote of an f-string. Unlike
+    /// `Str`/`Bytes`, the body can't be scanned eagerly here because it may
+    /// contain nested `{ ... }` expressions that are themselves ordinary
+    /// Python tokens; the outer `Lexer` drives that sub-lexing and emits the
+    /// `FStringMiddle`/`LeftBrace`/`RightBrace`/`FStringEnd` tokens that follow.
+    #[regex(r#"(?i:f|rf|fr)("""|'''|"|')"#, lex_fstring_start)]
+    FStringStart(FStringOpener),
+
+    /// A literal text chunk between `{ ... }` expressions in an f-string.
+    FStringMiddle(&'a str),
+    FStringEnd,
+
+    /// Delimits a `{ ... }` expression inside an f-string. Also doubles as
+    /// the general brace tokens once the grammar needs dict/set literals.
+    #[token("{")]
+    LeftBrace,
+    #[token("}")]
+    RightBrace,
+
+    /// The soft keyword `match`, recognized only by `SoftKeywords`; never
+    /// produced directly by `logos`.
+    Match,
+    /// The soft keyword `case`, recognized only by `SoftKeywords`; never
+    /// produced directly by `logos`.
+    Case,
+}
+
+/// The parsed body of a `Str`/`Bytes` literal: the slice between the quotes
+/// (escapes left untouched, for a later pass to interpret) and whether an
+/// `r` prefix was present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrBody<'a> {
+    pub value: &'a str,
+    pub raw: bool,
+}
+
+/// The prefix and quote style of an f-string's opening delimiter, carried
+/// forward so the outer `Lexer` knows how to find the matching terminator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FStringOpener {
+    pub quote: char,
+    pub triple: bool,
+    pub raw: bool,
+}
+
+/// Splits a matched opener slice (e.g. `rb"""`) into its prefix (`rb`), its
+/// quote character, and whether it's a triple-quote.
+fn split_prefix_and_quote(slice: &str) -> (&str, char, bool) {
+    let triple = slice.ends_with(r#"""""#) || slice.ends_with("'''");
+    let quote_len = if triple { 3 } else { 1 };
+    let prefix = &slice[..slice.len() - quote_len];
+    let quote = slice[slice.len() - quote_len..].chars().next().unwrap();
+    (prefix, quote, triple)
+}
+
+/// Scans `lex`'s remainder for the closing `quote` (tripled if `triple`),
+/// honoring backslash escapes so an escaped quote doesn't end the literal
+/// early, and bumps `lex` past everything it consumes. Returns an
+/// `UnterminatedString` error spanning to EOF if no terminator is found.
+fn scan_quoted<'a>(lex: &mut logos::Lexer<'a, Token<'a>>, quote: char, triple: bool) -> Result<&'a str, LexError> {
+    let remainder = lex.remainder();
+    let bytes = remainder.as_bytes();
+    let q = quote as u8;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i = (i + 2).min(bytes.len()),
+            b'\n' if !triple => {
+                lex.bump(remainder.len());
+                return Err(LexError { span: lex.span(), kind: LexErrorKind::UnterminatedString });
+            }
+            b if b == q => {
+                let closed = !triple || bytes[i..].starts_with(&[q, q, q]);
+                if !closed {
+                    i += 1;
+                    continue;
+                }
+                let delim_len = if triple { 3 } else { 1 };
+                let content = &remainder[..i];
+                lex.bump(i + delim_len);
+                return Ok(content);
+            }
+            _ => i += 1,
+        }
+    }
+
+    lex.bump(remainder.len());
+    Err(LexError { span: lex.span(), kind: LexErrorKind::UnterminatedString })
+}
+
+fn lex_str<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> Result<StrBody<'a>, LexError> {
+    let (prefix, quote, triple) = split_prefix_and_quote(lex.slice());
+    let value = scan_quoted(lex, quote, triple)?;
+    Ok(StrBody { value, raw: !prefix.is_empty() })
+}
+
+fn lex_bytes<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> Result<StrBody<'a>, LexError> {
+    let (prefix, quote, triple) = split_prefix_and_quote(lex.slice());
+    let value = scan_quoted(lex, quote, triple)?;
+    Ok(StrBody { value, raw: prefix.to_ascii_lowercase().contains('r') })
+}
+
+fn lex_fstring_start<'a>(lex: &mut logos::Lexer<'a, Token<'a>>) -> FStringOpener {
+    let (prefix, quote, triple) = split_prefix_and_quote(lex.slice());
+    FStringOpener { quote, triple, raw: prefix.to_ascii_lowercase().contains('r') }
 }
 
-pub fn lex(source: &'_ str) -> Vec<(Result<Token<'_>, ()>, Range<usize>)> {
-    let lexer = Token::lexer(source);
-    let mut indent_stack: Vec<usize> = vec![0];
-    let mut result: Vec<(Result<Token, ()>, Range<usize>)> = Vec::new();
+pub(crate) type Spanned<'a> = (Result<Token<'a>, LexError>, Range<usize>);
+
+/// The tab width CPython's own tokenizer assumes when expanding `\t` to the
+/// next multiple of this many columns.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Controls how leading whitespace is turned into an indentation column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentStyle {
+    tab_width: usize,
+}
 
-    for (token, span) in lexer.spanned() {
-        match token {
-            Ok(Token::Newline) => {
-                let slice = &source[span.clone()];
-                let indentation = slice.rsplit('\n').next().unwrap_or("").len();
+impl IndentStyle {
+    pub fn new(tab_width: usize) -> Self {
+        Self { tab_width }
+    }
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self::new(DEFAULT_TAB_WIDTH)
+    }
+}
+
+/// The visual column reached by a line's leading whitespace, measured two
+/// ways: once using the configured tab width, and once treating every `\t`
+/// as a single column. A dedent is only accepted as unambiguous when both
+/// measurements agree on its ordering against the enclosing block, mirroring
+/// how CPython's tokenizer rejects indentation whose meaning depends on the
+/// assumed tab size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IndentLevel {
+    column: usize,
+    alt_column: usize,
+}
 
-                result.push((Ok(Token::Newline), span.clone()));
+impl IndentLevel {
+    const ZERO: IndentLevel = IndentLevel { column: 0, alt_column: 0 };
 
-                let current = *indent_stack.last().unwrap();
-                if indentation > current {
-                    indent_stack.push(indentation);
-                    result.push((Ok(Token::Indentation), span.clone()));
-                } else if indentation < current {
-                    while *indent_stack.last().unwrap() > indentation {
-                        indent_stack.pop();
-                        result.push((Ok(Token::Deindentation), span.clone()));
+    fn measure(whitespace: &str, style: IndentStyle) -> (IndentLevel, bool) {
+        let mut column = 0;
+        let mut alt_column = 0;
+        let mut seen_space = false;
+        let mut tab_after_space = false;
+
+        for ch in whitespace.chars() {
+            match ch {
+                '\t' => {
+                    if seen_space {
+                        tab_after_space = true;
                     }
-                    if *indent_stack.last().unwrap() != indentation {
-                        result.push((Err(()), span.clone()));
+                    column = (column / style.tab_width + 1) * style.tab_width;
+                    alt_column += 1;
+                }
+                ' ' => {
+                    seen_space = true;
+                    column += 1;
+                    alt_column += 1;
+                }
+                _ => {}
+            }
+        }
+
+        (IndentLevel { column, alt_column }, tab_after_space)
+    }
+
+    /// Whether `self` orders the same way against `other` regardless of
+    /// which tab width produced it. A mismatch means the indentation is
+    /// ambiguous and must be rejected rather than silently resolved.
+    fn orders_unambiguously_against(&self, other: &IndentLevel) -> bool {
+        self.column.cmp(&other.column) == self.alt_column.cmp(&other.alt_column)
+    }
+}
+
+/// Lazily tokenizes `source`, synthesizing `Indentation`/`Deindentation`
+/// tokens around each `Newline` without buffering the whole token stream.
+///
+/// A single `Newline` can resolve to several tokens once the indent stack is
+/// consulted, so those extras are queued in `pending` and drained before the
+/// underlying `logos` lexer is advanced again.
+pub struct Lexer<'src> {
+    source: &'src str,
+    inner: logos::Lexer<'src, Token<'src>>,
+    style: IndentStyle,
+    indent_stack: Vec<IndentLevel>,
+    pending: VecDeque<Spanned<'src>>,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(source: &'src str, style: IndentStyle) -> Self {
+        Self {
+            source,
+            inner: Token::lexer(source),
+            style,
+            indent_stack: vec![IndentLevel::ZERO],
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn handle_newline(&mut self, span: Range<usize>) {
+        let slice = &self.source[span.clone()];
+        let whitespace = slice.rsplit('\n').next().unwrap_or("");
+        let (indent, tab_after_space) = IndentLevel::measure(whitespace, self.style);
+
+        let current = *self.indent_stack.last().unwrap();
+        if tab_after_space || !indent.orders_unambiguously_against(&current) {
+            self.push_error(span, LexErrorKind::TabSpaceMix);
+            return;
+        }
+
+        if indent.column > current.column {
+            self.indent_stack.push(indent);
+            self.pending.push_back((Ok(Token::Indentation), span));
+        } else if indent.column < current.column {
+            while {
+                let top = *self.indent_stack.last().unwrap();
+                top.column > indent.column
+            } {
+                self.indent_stack.pop();
+                self.pending.push_back((Ok(Token::Deindentation), span.clone()));
+            }
+
+            let top = *self.indent_stack.last().unwrap();
+            if top.column != indent.column || !top.orders_unambiguously_against(&indent) {
+                self.push_error(span, LexErrorKind::InconsistentDedent);
+            }
+        }
+    }
+
+    fn push_error(&mut self, span: Range<usize>, kind: LexErrorKind) {
+        self.pending.push_back((Err(LexError { span: span.clone(), kind }), span));
+    }
+
+    fn flush_eof(&mut self) {
+        let eof = self.source.len()..self.source.len();
+        while self.indent_stack.len() > 1 {
+            self.indent_stack.pop();
+            self.pending.push_back((Ok(Token::Deindentation), eof.clone()));
+        }
+    }
+
+    /// Drives the f-string sub-lexer after an `FStringStart(opener)` token.
+    ///
+    /// `logos` can't express this directly: the body alternates between raw
+    /// `FStringMiddle` text and `{ ... }` regions that must be re-lexed as
+    /// ordinary Python tokens, so this walks `self.source` by hand (mirroring
+    /// how a single `Newline` fans out into several queued tokens) and queues
+    /// everything through to the matching `FStringEnd` into `pending`, then
+    /// bumps the underlying `logos` lexer past all of it so normal
+    /// tokenization resumes right after the f-string.
+    fn lex_fstring_body(&mut self, opener: FStringOpener) {
+        let bytes = self.source.as_bytes();
+        let quote = opener.quote as u8;
+        let delim_len = if opener.triple { 3 } else { 1 };
+
+        let mut cursor = self.inner.span().end;
+        let mut run_start = cursor;
+
+        loop {
+            if cursor >= bytes.len() {
+                self.push_fstring_middle(run_start, cursor);
+                self.push_error(cursor..self.source.len(), LexErrorKind::UnterminatedString);
+                self.inner.bump(self.source.len() - self.inner.span().end);
+                return;
+            }
+
+            match bytes[cursor] {
+                b'\\' => cursor = (cursor + 2).min(bytes.len()),
+                b'\n' if !opener.triple => {
+                    self.push_fstring_middle(run_start, cursor);
+                    self.push_error(cursor..self.source.len(), LexErrorKind::UnterminatedString);
+                    self.inner.bump(self.source.len() - self.inner.span().end);
+                    return;
+                }
+                b if b == quote && (!opener.triple || bytes[cursor..].starts_with(&[quote, quote, quote])) => {
+                    self.push_fstring_middle(run_start, cursor);
+                    let end = cursor + delim_len;
+                    self.pending.push_back((Ok(Token::FStringEnd), cursor..end));
+                    self.inner.bump(end - self.inner.span().end);
+                    return;
+                }
+                b'{' if bytes.get(cursor + 1) == Some(&b'{') => {
+                    self.push_fstring_middle(run_start, cursor);
+                    self.pending.push_back((Ok(Token::FStringMiddle(&self.source[cursor..cursor + 1])), cursor..cursor + 1));
+                    cursor += 2;
+                    run_start = cursor;
+                }
+                b'}' if bytes.get(cursor + 1) == Some(&b'}') => {
+                    self.push_fstring_middle(run_start, cursor);
+                    self.pending.push_back((Ok(Token::FStringMiddle(&self.source[cursor..cursor + 1])), cursor..cursor + 1));
+                    cursor += 2;
+                    run_start = cursor;
+                }
+                b'{' => {
+                    self.push_fstring_middle(run_start, cursor);
+                    self.pending.push_back((Ok(Token::LeftBrace), cursor..cursor + 1));
+
+                    let expr_start = cursor + 1;
+                    let mut depth = 1usize;
+                    let mut j = expr_start;
+                    while j < bytes.len() && depth > 0 {
+                        match bytes[j] {
+                            b'{' => depth += 1,
+                            b'}' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth > 0 {
+                            j += 1;
+                        }
+                    }
+
+                    if depth != 0 {
+                        self.push_error(expr_start..self.source.len(), LexErrorKind::UnmatchedBrace);
+                        self.inner.bump(self.source.len() - self.inner.span().end);
+                        return;
+                    }
+
+                    // Re-lexed with a fresh nested `Lexer`, not a plain
+                    // `Token::lexer`, so a dict/set literal or a nested
+                    // f-string inside this expression gets the same
+                    // brace-tracking and sub-lexing treatment as the
+                    // top-level source.
+                    let expr_source = &self.source[expr_start..j];
+                    for (token, inner_span) in Lexer::new(expr_source, self.style) {
+                        self.pending.push_back((token, (inner_span.start + expr_start)..(inner_span.end + expr_start)));
                     }
+
+                    self.pending.push_back((Ok(Token::RightBrace), j..j + 1));
+                    cursor = j + 1;
+                    run_start = cursor;
+                }
+                b'}' => {
+                    self.push_fstring_middle(run_start, cursor);
+                    self.push_error(cursor..cursor + 1, LexErrorKind::UnmatchedBrace);
+                    self.inner.bump(self.source.len() - self.inner.span().end);
+                    return;
+                }
+                _ => cursor += 1,
+            }
+        }
+    }
+
+    fn push_fstring_middle(&mut self, run_start: usize, cursor: usize) {
+        if run_start < cursor {
+            self.pending.push_back((Ok(Token::FStringMiddle(&self.source[run_start..cursor])), run_start..cursor));
+        }
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Spanned<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.pending.pop_front() {
+            return Some(item);
+        }
+
+        match self.inner.next() {
+            Some(token) => {
+                let span = self.inner.span();
+                match token {
+                    Ok(Token::Newline) => {
+                        self.handle_newline(span.clone());
+                        Some((Ok(Token::Newline), span))
+                    }
+                    Ok(Token::FStringStart(opener)) => {
+                        self.lex_fstring_body(opener);
+                        Some((Ok(Token::FStringStart(opener)), span))
+                    }
+                    token => Some((token, span)),
                 }
             }
-            other => result.push((other, span)),
+            None => {
+                self.flush_eof();
+                self.pending.pop_front()
+            }
+        }
+    }
+}
+
+/// Whether `token` is an identifier this layer should consider rewriting
+/// into a soft keyword, pending a look at how its logical line ends.
+fn is_soft_keyword_candidate(token: &Result<Token<'_>, LexError>) -> bool {
+    matches!(token, Ok(Token::Identifier("match")) | Ok(Token::Identifier("case")))
+}
+
+/// Rewrites `Identifier("match")`/`Identifier("case")` into `Token::Match`/
+/// `Token::Case` when, and only when, they open a statement: the first
+/// significant token after a `Newline`/`Indentation`/`Deindentation`, in a
+/// logical line that ends in `:`. Everywhere else (`match = 3`, `obj.match()`,
+/// `match[0]`) they pass through unchanged.
+///
+/// Python's grammar only needs the trailing colon, not a full parse of what's
+/// in between, so this buffers one logical line ahead of whatever lexer it
+/// wraps and inspects the last token before that line's `Newline`.
+pub struct SoftKeywords<'src, I: Iterator<Item = Spanned<'src>>> {
+    inner: I,
+    buffer: VecDeque<Spanned<'src>>,
+    at_line_start: bool,
+}
+
+impl<'src, I: Iterator<Item = Spanned<'src>>> SoftKeywords<'src, I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            buffer: VecDeque::new(),
+            at_line_start: true,
+        }
+    }
+
+    /// Pulls tokens from `inner` into `buffer`, starting with `first`, up to
+    /// and including the `Newline` that ends the logical line (or until
+    /// `inner` runs out).
+    fn buffer_logical_line(&mut self, first: Spanned<'src>) {
+        self.buffer.push_back(first);
+        while !matches!(self.buffer.back(), Some((Ok(Token::Newline), _))) {
+            match self.inner.next() {
+                Some(item) => self.buffer.push_back(item),
+                None => break,
+            }
+        }
+    }
+
+    fn line_ends_in_colon(&self) -> bool {
+        let last_significant = match self.buffer.back() {
+            Some((Ok(Token::Newline), _)) => self.buffer.len().checked_sub(2),
+            _ => self.buffer.len().checked_sub(1),
+        };
+        matches!(last_significant.map(|i| &self.buffer[i].0), Some(Ok(Token::Colon)))
+    }
+
+    fn resolve_soft_keyword(&mut self) {
+        if !self.line_ends_in_colon() {
+            return;
+        }
+
+        let Some((token, _)) = self.buffer.front_mut() else {
+            return;
+        };
+        let name = match token {
+            Ok(Token::Identifier(name)) => *name,
+            _ => return,
+        };
+        *token = Ok(if name == "match" { Token::Match } else { Token::Case });
+    }
+
+    fn update_line_start(&mut self, token: &Result<Token<'src>, LexError>) {
+        self.at_line_start = matches!(
+            token,
+            Ok(Token::Newline) | Ok(Token::Indentation) | Ok(Token::Deindentation)
+        );
+    }
+}
+
+impl<'src, I: Iterator<Item = Spanned<'src>>> Iterator for SoftKeywords<'src, I> {
+    type Item = Spanned<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            let first = self.inner.next()?;
+            if self.at_line_start && is_soft_keyword_candidate(&first.0) {
+                self.buffer_logical_line(first);
+                self.resolve_soft_keyword();
+            } else {
+                self.buffer.push_back(first);
+            }
         }
+
+        let item = self.buffer.pop_front().expect("buffer was just populated");
+        self.update_line_start(&item.0);
+        Some(item)
+    }
+}
+
+pub fn lex(source: &'_ str, style: IndentStyle) -> Vec<Spanned<'_>> {
+    SoftKeywords::new(Lexer::new(source, style)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The shape of a `Token` that fixtures compare against: same variant,
+    /// ignoring payload and span. Hand-tracing exact `&str` slices and byte
+    /// offsets for every fixture would defeat the point of writing them
+    /// tersely, so fixtures only name kinds.
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Definition,
+        Identifier,
+        LeftParentheses,
+        RightParentheses,
+        Arrow,
+        Colon,
+        Newline,
+        Indentation,
+        Deindentation,
+        Return,
+        Integer,
+        Str,
+        Bytes,
+        FStringStart,
+        FStringMiddle,
+        FStringEnd,
+        LeftBrace,
+        RightBrace,
+        Match,
+        Case,
+        Error,
+    }
+
+    impl From<&Result<Token<'_>, LexError>> for Kind {
+        fn from(token: &Result<Token<'_>, LexError>) -> Self {
+            match token {
+                Ok(Token::Definition) => Kind::Definition,
+                Ok(Token::Identifier(_)) => Kind::Identifier,
+                Ok(Token::LeftParentheses) => Kind::LeftParentheses,
+                Ok(Token::RightParentheses) => Kind::RightParentheses,
+                Ok(Token::Arrow) => Kind::Arrow,
+                Ok(Token::Colon) => Kind::Colon,
+                Ok(Token::Newline) => Kind::Newline,
+                Ok(Token::Indentation) => Kind::Indentation,
+                Ok(Token::Deindentation) => Kind::Deindentation,
+                Ok(Token::Return) => Kind::Return,
+                Ok(Token::Integer(_)) => Kind::Integer,
+                Ok(Token::Str(_)) => Kind::Str,
+                Ok(Token::Bytes(_)) => Kind::Bytes,
+                Ok(Token::FStringStart(_)) => Kind::FStringStart,
+                Ok(Token::FStringMiddle(_)) => Kind::FStringMiddle,
+                Ok(Token::FStringEnd) => Kind::FStringEnd,
+                Ok(Token::LeftBrace) => Kind::LeftBrace,
+                Ok(Token::RightBrace) => Kind::RightBrace,
+                Ok(Token::Match) => Kind::Match,
+                Ok(Token::Case) => Kind::Case,
+                Err(_) => Kind::Error,
+            }
+        }
+    }
+
+    impl std::str::FromStr for Kind {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(match s {
+                "Definition" => Kind::Definition,
+                "Identifier" => Kind::Identifier,
+                "LeftParentheses" => Kind::LeftParentheses,
+                "RightParentheses" => Kind::RightParentheses,
+                "Arrow" => Kind::Arrow,
+                "Colon" => Kind::Colon,
+                "Newline" => Kind::Newline,
+                "Indentation" => Kind::Indentation,
+                "Deindentation" => Kind::Deindentation,
+                "Return" => Kind::Return,
+                "Integer" => Kind::Integer,
+                "Str" => Kind::Str,
+                "Bytes" => Kind::Bytes,
+                "FStringStart" => Kind::FStringStart,
+                "FStringMiddle" => Kind::FStringMiddle,
+                "FStringEnd" => Kind::FStringEnd,
+                "LeftBrace" => Kind::LeftBrace,
+                "RightBrace" => Kind::RightBrace,
+                "Match" => Kind::Match,
+                "Case" => Kind::Case,
+                "Error" => Kind::Error,
+                other => return Err(format!("unknown token kind `{other}`")),
+            })
+        }
+    }
+
+    /// Lexes the Python source in `fixture` and asserts it produces exactly
+    /// the token kinds listed after the `---` separator line, e.g.:
+    ///
+    /// ```text
+    /// def f():
+    ///     return 1
+    /// ---
+    /// Definition Identifier LeftParentheses RightParentheses Colon Newline
+    /// Indentation Return Integer Newline Deindentation
+    /// ```
+    ///
+    /// Spans and token payloads (identifier text, integer values, ...) are
+    /// ignored; only the sequence of kinds is checked.
+    fn check(fixture: &str) {
+        let (source, expected) = split_fixture(fixture);
+
+        let expected: Vec<Kind> = expected.split_whitespace().map(|name| name.parse().unwrap()).collect();
+        let actual: Vec<Kind> = lex(source, IndentStyle::default()).iter().map(|(token, _)| Kind::from(token)).collect();
+
+        assert_eq!(actual, expected, "unexpected token kinds for fixture source:\n{source}");
+    }
+
+    /// Splits a fixture into `(source, expected)` at the `---` line.
+    ///
+    /// Looks for `---` followed by a newline or end of input, rather than
+    /// requiring a preceding `\n`, so that a fixture whose Python source
+    /// itself ends in `\n` (the common case) keeps that newline as part of
+    /// `source` instead of having it swallowed as separator punctuation.
+    fn split_fixture(fixture: &str) -> (&str, &str) {
+        let bytes = fixture.as_bytes();
+        let mut idx = 0;
+        while let Some(rel) = fixture[idx..].find("---") {
+            let start = idx + rel;
+            let end = start + 3;
+            if end == bytes.len() || bytes[end] == b'\n' {
+                let expected_start = if end == bytes.len() { end } else { end + 1 };
+                return (&fixture[..start], &fixture[expected_start..]);
+            }
+            idx = end;
+        }
+        panic!("fixture must have a `---` line separating source from expected kinds")
+    }
+
+    #[test]
+    fn function_with_indented_body() {
+        check(
+            "def f():\n    return 1\n\
+             ---\n\
+             Definition Identifier LeftParentheses RightParentheses Colon Newline \
+             Indentation Return Integer Newline Deindentation",
+        );
+    }
+
+    #[test]
+    fn nested_indentation() {
+        check(
+            "def f():\n    def g():\n        return 1\n    return 2\n\
+             ---\n\
+             Definition Identifier LeftParentheses RightParentheses Colon Newline Indentation \
+             Definition Identifier LeftParentheses RightParentheses Colon Newline Indentation \
+             Return Integer Newline Deindentation \
+             Return Integer Newline Deindentation",
+        );
+    }
+
+    #[test]
+    fn eof_flushes_pending_dedents() {
+        // No trailing newline: the closing `Deindentation` has to come from
+        // `flush_eof`, not from a `Newline` measuring the next line.
+        check(
+            "def f():\n    return 1\
+             ---\n\
+             Definition Identifier LeftParentheses RightParentheses Colon Newline \
+             Indentation Return Integer Deindentation",
+        );
+    }
+
+    #[test]
+    fn blank_line_does_not_perturb_indentation() {
+        // The blank line between the two `return`s must not produce a spurious
+        // Indentation/Deindentation pair around it.
+        check(
+            "def f():\n    return 1\n\n    return 2\n\
+             ---\n\
+             Definition Identifier LeftParentheses RightParentheses Colon Newline Indentation \
+             Return Integer Newline \
+             Return Integer Newline Deindentation",
+        );
+    }
+
+    #[test]
+    fn str_and_bytes_literals() {
+        check(
+            "return 'hi'\nreturn b'hi'\n\
+             ---\n\
+             Return Str Newline Return Bytes Newline",
+        );
+    }
+
+    #[test]
+    fn fstring_with_interpolation() {
+        check(
+            "return f'a{x}b'\n\
+             ---\n\
+             Return FStringStart FStringMiddle LeftBrace Identifier RightBrace FStringMiddle FStringEnd Newline",
+        );
+    }
+
+    #[test]
+    fn fstring_expression_with_brace_literal() {
+        // The `{'a': 1}` dict literal inside the substitution expression has
+        // to come back as real `LeftBrace`/`RightBrace` tokens, not get eaten
+        // as `InvalidToken` errors by a sub-lexer that doesn't know `{`/`}`.
+        check(
+            "return f\"{ {'a': 1} }\"\n\
+             ---\n\
+             Return FStringStart LeftBrace LeftBrace Str Colon Integer RightBrace RightBrace FStringEnd Newline",
+        );
+    }
+
+    #[test]
+    fn fstring_nested_fstring_expression() {
+        // A nested f-string inside the substitution expression needs its own
+        // sub-lexing pass too, not just its opening `FStringStart` token.
+        check(
+            "return f\"{f'{x}'}\"\n\
+             ---\n\
+             Return FStringStart LeftBrace FStringStart LeftBrace Identifier RightBrace FStringEnd RightBrace FStringEnd Newline",
+        );
+    }
+
+    #[test]
+    fn match_and_case_open_a_match_statement() {
+        check(
+            "match x:\n    case 1:\n        return 1\n\
+             ---\n\
+             Match Identifier Colon Newline Indentation \
+             Case Integer Colon Newline Indentation \
+             Return Integer Newline Deindentation \
+             Deindentation",
+        );
+    }
+
+    #[test]
+    fn match_stays_an_identifier_away_from_statement_position() {
+        // Not first on its logical line, so it can't be opening a `match`
+        // statement regardless of what follows.
+        check("return match\n---\nReturn Identifier Newline");
     }
 
-    let eof = source.len()..source.len();
-    while indent_stack.len() > 1 {
-        indent_stack.pop();
-        result.push((Ok(Token::Deindentation), eof.clone()));
+    #[test]
+    fn match_stays_an_identifier_without_a_trailing_colon() {
+        // First on its logical line, but the line never reaches a `:`, so
+        // this is `match` the variable, not `match` the statement.
+        check("match\n---\nIdentifier Newline");
     }
-    result
 }
@@ -1,21 +1,53 @@
-use crate::lexer::lex;
-use std::{env, fs, path};
+use crate::diagnostics::{render, LineIndex};
+use crate::lexer::{lex, IndentStyle};
+use crate::parser::{parse, ParseError};
+use std::{env, fs, path, process};
 
+mod diagnostics;
 mod lexer;
+mod parser;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let print_ast = args.iter().any(|arg| arg == "--ast");
+    let Some(path_arg) = args.iter().find(|arg| *arg != "--ast") else {
         eprintln!("Invalid usage");
         eprintln!("Usage:");
-        eprintln!("    pyrsc <input.py>");
+        eprintln!("    pyrsc [--ast] <input.py>");
         return;
-    }
+    };
+
+    let content = fs::read_to_string(path::Path::new(path_arg)).unwrap();
+    let index = LineIndex::new(&content);
+    let tokens = lex(&content, IndentStyle::default());
 
-    let content = fs::read_to_string(path::Path::new(&args[1])).unwrap();
-    let tokens = lex(&content);
+    if print_ast {
+        match parse(tokens) {
+            Ok(program) => println!("{program:#?}"),
+            Err(ParseError::Lex(error)) => {
+                eprintln!("{}", render(path_arg, &content, &index, error.span.clone(), &error));
+                process::exit(1);
+            }
+            Err(error) => {
+                eprintln!("{path_arg}: {error:?}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
 
+    let mut had_error = false;
     for (token, span) in tokens {
-        println!("{:?}: {:?}", token.unwrap(), span)
+        match token {
+            Ok(token) => println!("{token:?}: {span:?}"),
+            Err(error) => {
+                eprintln!("{}", render(path_arg, &content, &index, span, &error));
+                had_error = true;
+            }
+        }
+    }
+
+    if had_error {
+        process::exit(1);
     }
 }
@@ -0,0 +1,253 @@
+use crate::lexer::{LexError, Spanned, Token};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<'a> {
+    Identifier(&'a str),
+    Integer(i64),
+    Call { callee: Box<Expr<'a>>, args: Vec<Expr<'a>> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt<'a> {
+    FunctionDef {
+        name: &'a str,
+        params: Vec<&'a str>,
+        return_type: Option<&'a str>,
+        body: Vec<Stmt<'a>>,
+    },
+    Return(Option<Expr<'a>>),
+    Expr(Expr<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The lexer produced an error token where the parser expected a valid
+    /// one; the error's span is the one paired with it in the token stream.
+    Lex(LexError),
+    UnexpectedEof,
+    Unexpected { span: Range<usize>, expected: &'static str },
+}
+
+type PResult<T> = Result<T, ParseError>;
+
+/// Consumes the `Indentation`/`Deindentation`-annotated token stream `lex`
+/// produces and builds an AST out of it. The synthesized indent tokens are
+/// treated like explicit open/close brackets: a run of statements between a
+/// matching `Indentation` and its `Deindentation` is one block, the same way
+/// an off-side-rule language is reduced to a bracketed grammar before parsing.
+pub fn parse(tokens: Vec<Spanned<'_>>) -> Result<Vec<Stmt<'_>>, ParseError> {
+    Parser::new(tokens).parse_program()
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: Vec<Spanned<'a>>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn current(&self) -> PResult<&Token<'a>> {
+        match self.tokens.get(self.pos) {
+            Some((Ok(token), _)) => Ok(token),
+            Some((Err(error), span)) => Err(ParseError::Lex(LexError { span: span.clone(), ..error.clone() })),
+            None => Err(ParseError::UnexpectedEof),
+        }
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.tokens.get(self.pos).map_or(0..0, |(_, span)| span.clone())
+    }
+
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    fn expect(&mut self, expected: Token<'a>, what: &'static str) -> PResult<()> {
+        if self.current()? == &expected {
+            self.bump();
+            Ok(())
+        } else {
+            Err(ParseError::Unexpected { span: self.span(), expected: what })
+        }
+    }
+
+    fn expect_identifier(&mut self, what: &'static str) -> PResult<&'a str> {
+        match self.current()? {
+            Token::Identifier(name) => {
+                let name = *name;
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(ParseError::Unexpected { span: self.span(), expected: what }),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while matches!(self.current(), Ok(Token::Newline)) {
+            self.bump();
+        }
+    }
+
+    fn parse_program(&mut self) -> PResult<Vec<Stmt<'a>>> {
+        let mut stmts = Vec::new();
+        self.skip_newlines();
+        while self.pos < self.tokens.len() {
+            stmts.push(self.parse_stmt()?);
+            self.skip_newlines();
+        }
+        Ok(stmts)
+    }
+
+    /// Parses the body of a block: an `Indentation`, statements up to the
+    /// matching `Deindentation`, then that `Deindentation`.
+    fn parse_block(&mut self) -> PResult<Vec<Stmt<'a>>> {
+        self.expect(Token::Indentation, "indented block")?;
+
+        let mut stmts = Vec::new();
+        loop {
+            self.skip_newlines();
+            if matches!(self.current(), Ok(Token::Deindentation)) {
+                self.bump();
+                return Ok(stmts);
+            }
+            stmts.push(self.parse_stmt()?);
+        }
+    }
+
+    fn parse_stmt(&mut self) -> PResult<Stmt<'a>> {
+        match self.current()? {
+            Token::Definition => self.parse_function_def(),
+            Token::Return => self.parse_return(),
+            _ => self.parse_expr_stmt(),
+        }
+    }
+
+    fn parse_function_def(&mut self) -> PResult<Stmt<'a>> {
+        self.expect(Token::Definition, "'def'")?;
+        let name = self.expect_identifier("function name")?;
+
+        self.expect(Token::LeftParentheses, "'('")?;
+        // The lexer has no `,` token yet, so parameters are just whatever
+        // identifiers appear back to back before the closing `)`.
+        let mut params = Vec::new();
+        while matches!(self.current(), Ok(Token::Identifier(_))) {
+            params.push(self.expect_identifier("parameter name")?);
+        }
+        self.expect(Token::RightParentheses, "')'")?;
+
+        let return_type = if matches!(self.current(), Ok(Token::Arrow)) {
+            self.bump();
+            Some(self.expect_identifier("return type")?)
+        } else {
+            None
+        };
+
+        self.expect(Token::Colon, "':'")?;
+        self.expect(Token::Newline, "newline before indented block")?;
+        let body = self.parse_block()?;
+
+        Ok(Stmt::FunctionDef { name, params, return_type, body })
+    }
+
+    fn parse_return(&mut self) -> PResult<Stmt<'a>> {
+        self.expect(Token::Return, "'return'")?;
+        let value = match self.current() {
+            Ok(Token::Newline) | Ok(Token::Deindentation) | Err(ParseError::UnexpectedEof) => None,
+            _ => Some(self.parse_expr()?),
+        };
+        self.finish_stmt()?;
+        Ok(Stmt::Return(value))
+    }
+
+    fn parse_expr_stmt(&mut self) -> PResult<Stmt<'a>> {
+        let expr = self.parse_expr()?;
+        self.finish_stmt()?;
+        Ok(Stmt::Expr(expr))
+    }
+
+    /// A statement ends at a `Newline` or, for the last statement in the
+    /// file, at EOF.
+    fn finish_stmt(&mut self) -> PResult<()> {
+        match self.current() {
+            Ok(Token::Newline) => {
+                self.bump();
+                Ok(())
+            }
+            // Inside a block, EOF shows up as the `Deindentation`s the lexer
+            // flushes there; leave them for `parse_block` to consume.
+            Ok(Token::Deindentation) | Err(ParseError::UnexpectedEof) => Ok(()),
+            Err(error @ ParseError::Lex(_)) => Err(error),
+            _ => Err(ParseError::Unexpected { span: self.span(), expected: "end of statement" }),
+        }
+    }
+
+    fn parse_expr(&mut self) -> PResult<Expr<'a>> {
+        let mut expr = self.parse_atom()?;
+        while matches!(self.current(), Ok(Token::LeftParentheses)) {
+            self.bump();
+            let mut args = Vec::new();
+            while !matches!(self.current(), Ok(Token::RightParentheses)) {
+                args.push(self.parse_expr()?);
+            }
+            self.expect(Token::RightParentheses, "')'")?;
+            expr = Expr::Call { callee: Box::new(expr), args };
+        }
+        Ok(expr)
+    }
+
+    fn parse_atom(&mut self) -> PResult<Expr<'a>> {
+        match self.current()? {
+            Token::Identifier(name) => {
+                let name = *name;
+                self.bump();
+                Ok(Expr::Identifier(name))
+            }
+            Token::Integer(value) => {
+                let value = *value;
+                self.bump();
+                Ok(Expr::Integer(value))
+            }
+            _ => Err(ParseError::Unexpected { span: self.span(), expected: "expression" }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{lex, IndentStyle};
+
+    fn parse_source(source: &str) -> Result<Vec<Stmt<'_>>, ParseError> {
+        parse(lex(source, IndentStyle::default()))
+    }
+
+    #[test]
+    fn nested_block_becomes_a_function_def_with_a_statement_list_body() {
+        let program = parse_source("def f():\n    return 1\n    return 2\n").unwrap();
+
+        assert_eq!(
+            program,
+            vec![Stmt::FunctionDef {
+                name: "f",
+                params: vec![],
+                return_type: None,
+                body: vec![Stmt::Return(Some(Expr::Integer(1))), Stmt::Return(Some(Expr::Integer(2)))],
+            }]
+        );
+    }
+
+    /// Regression test: a lexer failure where a statement is expected to end
+    /// (e.g. an unterminated string starting where `finish_stmt` looks for a
+    /// `Newline`) must surface as `ParseError::Lex`, not get discarded into a
+    /// generic `Unexpected { expected: "end of statement" }`.
+    #[test]
+    fn finish_stmt_propagates_a_lex_error_instead_of_reporting_unexpected() {
+        let error = parse_source("def f():\n    return 1\n    return 2 \"oops\n").unwrap_err();
+
+        assert!(matches!(error, ParseError::Lex(LexError { kind: crate::lexer::LexErrorKind::UnterminatedString, .. })));
+    }
+}